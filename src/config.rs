@@ -0,0 +1,159 @@
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+use sdl2::keyboard::Keycode;
+
+use crate::versus::KeyBindings;
+
+const CONFIG_FILE_NAME: &str = "config.txt";
+
+/// User-editable settings: the key bound to each action, plus a couple of
+/// gameplay options. Loaded once in `main()` and threaded into
+/// `handle_events` instead of the keycodes it used to hardcode.
+pub struct Controls {
+    pub player_one: KeyBindings,
+    pub player_two: KeyBindings,
+    pub pause: Keycode,
+    pub starting_level: u32,
+    pub audio_enabled: bool,
+}
+
+impl Default for Controls {
+    fn default() -> Controls {
+        Controls {
+            player_one: KeyBindings {
+                left: Keycode::A,
+                right: Keycode::D,
+                down: Keycode::S,
+                rotate: Keycode::W,
+                drop: Keycode::LShift,
+                hold: Keycode::LCtrl,
+            },
+            player_two: KeyBindings {
+                left: Keycode::Left,
+                right: Keycode::Right,
+                down: Keycode::Down,
+                rotate: Keycode::Up,
+                drop: Keycode::RShift,
+                hold: Keycode::RCtrl,
+            },
+            pause: Keycode::P,
+            starting_level: 1,
+            audio_enabled: true,
+        }
+    }
+}
+
+fn config_file_path() -> PathBuf {
+    let config_dir = dirs::config_dir()
+        .map(|dir| dir.join("tetrimino"))
+        .unwrap_or_else(|| PathBuf::from("."));
+    config_dir.join(CONFIG_FILE_NAME)
+}
+
+/// Loads the config file, generating a default one on first run.
+pub fn load_or_create() -> Controls {
+    let path = config_file_path();
+
+    match fs::read_to_string(&path) {
+        Ok(contents) => parse(&contents),
+        Err(_) => {
+            let controls = Controls::default();
+            save(&controls);
+            controls
+        }
+    }
+}
+
+fn parse(contents: &str) -> Controls {
+    let mut controls = Controls::default();
+
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let mut parts = line.splitn(2, '=');
+        let key = parts.next().unwrap_or("").trim();
+        let value = parts.next().unwrap_or("").trim();
+
+        match key {
+            "player_one.left" => set_keycode(&mut controls.player_one.left, value),
+            "player_one.right" => set_keycode(&mut controls.player_one.right, value),
+            "player_one.down" => set_keycode(&mut controls.player_one.down, value),
+            "player_one.rotate" => set_keycode(&mut controls.player_one.rotate, value),
+            "player_one.drop" => set_keycode(&mut controls.player_one.drop, value),
+            "player_one.hold" => set_keycode(&mut controls.player_one.hold, value),
+            "player_two.left" => set_keycode(&mut controls.player_two.left, value),
+            "player_two.right" => set_keycode(&mut controls.player_two.right, value),
+            "player_two.down" => set_keycode(&mut controls.player_two.down, value),
+            "player_two.rotate" => set_keycode(&mut controls.player_two.rotate, value),
+            "player_two.drop" => set_keycode(&mut controls.player_two.drop, value),
+            "player_two.hold" => set_keycode(&mut controls.player_two.hold, value),
+            "pause" => set_keycode(&mut controls.pause, value),
+            "starting_level" => {
+                if let Ok(level) = value.parse() {
+                    controls.starting_level = level;
+                }
+            }
+            "audio_enabled" => controls.audio_enabled = value.eq_ignore_ascii_case("true"),
+            _ => {}
+        }
+    }
+
+    controls
+}
+
+fn set_keycode(slot: &mut Keycode, value: &str) {
+    if let Some(keycode) = Keycode::from_name(value) {
+        *slot = keycode;
+    }
+}
+
+/// Writes `controls` to the config file, one `key = value` line per setting,
+/// in the form `parse` reads back. Any existing file is overwritten.
+pub fn save(controls: &Controls) {
+    let path = config_file_path();
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+
+    let contents = format!(
+        "player_one.left = {}\n\
+         player_one.right = {}\n\
+         player_one.down = {}\n\
+         player_one.rotate = {}\n\
+         player_one.drop = {}\n\
+         player_one.hold = {}\n\
+         player_two.left = {}\n\
+         player_two.right = {}\n\
+         player_two.down = {}\n\
+         player_two.rotate = {}\n\
+         player_two.drop = {}\n\
+         player_two.hold = {}\n\
+         pause = {}\n\
+         starting_level = {}\n\
+         audio_enabled = {}\n",
+        controls.player_one.left,
+        controls.player_one.right,
+        controls.player_one.down,
+        controls.player_one.rotate,
+        controls.player_one.drop,
+        controls.player_one.hold,
+        controls.player_two.left,
+        controls.player_two.right,
+        controls.player_two.down,
+        controls.player_two.rotate,
+        controls.player_two.drop,
+        controls.player_two.hold,
+        controls.pause,
+        controls.starting_level,
+        controls.audio_enabled,
+    );
+
+    if let Ok(mut file) = fs::File::create(&path) {
+        let _ = file.write_all(contents.as_bytes());
+    }
+}
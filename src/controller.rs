@@ -0,0 +1,67 @@
+use std::time::{Duration, SystemTime};
+
+pub const AXIS_DEAD_ZONE: i16 = 8_000;
+const MOVE_REPEAT_DELAY: Duration = Duration::from_millis(150);
+
+/// Tracks when each direction was last actuated by a held stick/D-pad so
+/// holding it moves the piece at a controlled rate instead of every frame.
+///
+/// The stick/D-pad state itself (`axis_x`/`axis_y`/`dpad_*`) is latched from
+/// `Event::ControllerAxisMotion`/`Event::ControllerButtonDown`/`ButtonUp`
+/// rather than polled, since those events only fire on change and the held
+/// state has to persist across the frames in between.
+pub struct ControllerRepeat {
+    last_left: SystemTime,
+    last_right: SystemTime,
+    last_down: SystemTime,
+    last_up: SystemTime,
+    pub axis_x: i16,
+    pub axis_y: i16,
+    pub dpad_left: bool,
+    pub dpad_right: bool,
+    pub dpad_up: bool,
+    pub dpad_down: bool,
+}
+
+impl ControllerRepeat {
+    pub fn new() -> ControllerRepeat {
+        let epoch = SystemTime::now() - MOVE_REPEAT_DELAY;
+        ControllerRepeat {
+            last_left: epoch,
+            last_right: epoch,
+            last_down: epoch,
+            last_up: epoch,
+            axis_x: 0,
+            axis_y: 0,
+            dpad_left: false,
+            dpad_right: false,
+            dpad_up: false,
+            dpad_down: false,
+        }
+    }
+
+    pub fn ready_left(&mut self) -> bool {
+        Self::ready(&mut self.last_left)
+    }
+
+    pub fn ready_right(&mut self) -> bool {
+        Self::ready(&mut self.last_right)
+    }
+
+    pub fn ready_down(&mut self) -> bool {
+        Self::ready(&mut self.last_down)
+    }
+
+    pub fn ready_up(&mut self) -> bool {
+        Self::ready(&mut self.last_up)
+    }
+
+    fn ready(last: &mut SystemTime) -> bool {
+        if last.elapsed().unwrap_or(MOVE_REPEAT_DELAY) >= MOVE_REPEAT_DELAY {
+            *last = SystemTime::now();
+            true
+        } else {
+            false
+        }
+    }
+}
@@ -1,6 +1,8 @@
+use std::collections::VecDeque;
 use std::thread;
 use std::time::{Duration, SystemTime};
 
+use sdl2::controller::{Axis, Button, GameController};
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
 use sdl2::pixels::Color;
@@ -8,16 +10,26 @@ use sdl2::rect::Rect;
 use sdl2::render::{Canvas, Texture, TextureCreator};
 use sdl2::ttf::Font;
 use sdl2::video::{Window, WindowContext};
-use sdl2::EventPump;
 
+mod config;
+mod controller;
 mod score;
 mod shape;
+mod sound;
+mod state;
 mod tetrimino;
+mod versus;
 
-use crate::tetrimino::Tetris;
+use crate::controller::{ControllerRepeat, AXIS_DEAD_ZONE};
+use crate::sound::{Sfx, SoundBank};
+use crate::state::{GameMode, GameState};
+use crate::tetrimino::{Tetris, Tetrimino};
+use crate::versus::{send_garbage, KeyBindings};
 
 const NB_HIGHSOCRES: usize = 5;
 const TETRIS_HEIGHT: usize = 40;
+const NEXT_QUEUE_LEN: usize = 3;
+const PREVIEW_CELL: u32 = 20;
 
 fn create_texture_rect<'a>(
     canvas: &mut Canvas<Window>,
@@ -64,17 +76,49 @@ fn handle_events(
     tetris: &mut Tetris,
     quit: &mut bool,
     timer: &mut SystemTime,
-    event_pump: &mut EventPump,
+    events: &[Event],
+    keys: &KeyBindings,
+    controller: Option<&GameController>,
+    repeat: &mut ControllerRepeat,
+    sounds: Option<&SoundBank>,
+    queue: &mut VecDeque<Tetrimino>,
+    held: &mut Option<Tetrimino>,
+    hold_used: &mut bool,
+    round_over: &mut bool,
 ) -> bool {
     let mut make_permanent = false;
+    let mut hard_drop_requested = false;
+
+    if !*hold_used {
+        for event in events {
+            if let Event::KeyDown {
+                keycode: Some(keycode),
+                ..
+            } = event
+            {
+                if *keycode == keys.hold {
+                    if !hold_piece(tetris, queue, held) {
+                        play_sound(sounds, Sfx::GameOver);
+                        *round_over = true;
+                    }
+                    *hold_used = true;
+                    break;
+                }
+            }
+        }
+    }
+
     if let Some(ref mut piece) = tetris.current_piece {
         let mut tmp_x = piece.x;
         let mut tmp_y = piece.y;
 
-        for event in event_pump.poll_iter() {
+        for event in events {
             match event {
-                Event::Quit { .. }
-                | Event::KeyDown {
+                Event::Quit { .. } => {
+                    *quit = true;
+                    break;
+                }
+                Event::KeyDown {
                     keycode: Some(Keycode::Escape),
                     ..
                 } => {
@@ -82,45 +126,98 @@ fn handle_events(
                     break;
                 }
                 Event::KeyDown {
-                    keycode: Some(Keycode::Down),
+                    keycode: Some(keycode),
                     ..
-                } => {
+                } if *keycode == keys.down => {
                     *timer = SystemTime::now();
                     tmp_y += 1;
                 }
                 Event::KeyDown {
-                    keycode: Some(Keycode::Right),
+                    keycode: Some(keycode),
                     ..
-                } => {
+                } if *keycode == keys.right => {
                     tmp_x += 1;
                 }
                 Event::KeyDown {
-                    keycode: Some(Keycode::Left),
+                    keycode: Some(keycode),
                     ..
-                } => {
+                } if *keycode == keys.left => {
                     tmp_x -= 1;
                 }
                 Event::KeyDown {
-                    keycode: Some(Keycode::Up),
+                    keycode: Some(keycode),
                     ..
-                } => {
+                } if *keycode == keys.rotate => {
                     piece.rotate(&tetris.game_map);
+                    play_sound(sounds, Sfx::Rotate);
                 }
                 Event::KeyDown {
-                    keycode: Some(Keycode::Space),
+                    keycode: Some(keycode),
                     ..
-                } => {
-                    let x = piece.x;
-                    let mut y = piece.y;
-                    while piece.change_position(&tetris.game_map, x, y + 1) == true {
-                        y += 1;
+                } if *keycode == keys.drop => {
+                    hard_drop_requested = true;
+                }
+                Event::ControllerButtonDown { which, button, .. }
+                    if controller.map_or(false, |c| c.instance_id() == *which) =>
+                {
+                    match button {
+                        Button::A => hard_drop_requested = true,
+                        Button::DPadLeft => repeat.dpad_left = true,
+                        Button::DPadRight => repeat.dpad_right = true,
+                        Button::DPadUp => repeat.dpad_up = true,
+                        Button::DPadDown => repeat.dpad_down = true,
+                        _ => {}
+                    }
+                }
+                Event::ControllerButtonUp { which, button, .. }
+                    if controller.map_or(false, |c| c.instance_id() == *which) =>
+                {
+                    match button {
+                        Button::DPadLeft => repeat.dpad_left = false,
+                        Button::DPadRight => repeat.dpad_right = false,
+                        Button::DPadUp => repeat.dpad_up = false,
+                        Button::DPadDown => repeat.dpad_down = false,
+                        _ => {}
                     }
-                    make_permanent = true;
                 }
+                Event::ControllerAxisMotion {
+                    which, axis, value, ..
+                } if controller.map_or(false, |c| c.instance_id() == *which) => match axis {
+                    Axis::LeftX => repeat.axis_x = *value,
+                    Axis::LeftY => repeat.axis_y = *value,
+                    _ => {}
+                },
                 _ => {}
             }
         }
 
+        if controller.is_some() {
+            if (repeat.axis_x < -AXIS_DEAD_ZONE || repeat.dpad_left) && repeat.ready_left() {
+                tmp_x -= 1;
+            } else if (repeat.axis_x > AXIS_DEAD_ZONE || repeat.dpad_right) && repeat.ready_right()
+            {
+                tmp_x += 1;
+            }
+
+            if (repeat.axis_y > AXIS_DEAD_ZONE || repeat.dpad_down) && repeat.ready_down() {
+                *timer = SystemTime::now();
+                tmp_y += 1;
+            } else if (repeat.axis_y < -AXIS_DEAD_ZONE || repeat.dpad_up) && repeat.ready_up() {
+                piece.rotate(&tetris.game_map);
+                play_sound(sounds, Sfx::Rotate);
+            }
+        }
+
+        if hard_drop_requested {
+            let x = piece.x;
+            let mut y = piece.y;
+            while piece.change_position(&tetris.game_map, x, y + 1) == true {
+                y += 1;
+            }
+            make_permanent = true;
+            play_sound(sounds, Sfx::HardDrop);
+        }
+
         if !make_permanent {
             if piece.change_position(&tetris.game_map, tmp_x, tmp_y) == false && tmp_y != piece.y {
                 make_permanent = true;
@@ -129,13 +226,64 @@ fn handle_events(
     }
 
     if make_permanent {
+        let lines_before = tetris.nb_lines;
         tetris.make_permanent();
+        play_lock_sound(sounds, tetris.nb_lines - lines_before);
         *timer = SystemTime::now();
+        *hold_used = false;
     }
 
     make_permanent
 }
 
+/// Swaps the active piece into the hold slot, respawning it from the held
+/// piece (or the look-ahead queue, refilled to keep its length constant).
+/// Returns `false` if the respawned piece doesn't fit, the same top-out
+/// condition the normal spawn path checks for.
+fn hold_piece(tetris: &mut Tetris, queue: &mut VecDeque<Tetrimino>, held: &mut Option<Tetrimino>) -> bool {
+    if let Some(mut current) = tetris.current_piece.take() {
+        current.x = 4;
+        current.y = 0;
+        current.current_state = 0;
+
+        let next = match held.take() {
+            Some(piece) => piece,
+            None => {
+                let piece = queue
+                    .pop_front()
+                    .unwrap_or_else(|| tetris.create_new_tetrimino());
+                queue.push_back(tetris.create_new_tetrimino());
+                piece
+            }
+        };
+
+        let fits = next.test_current_position(&tetris.game_map);
+        *held = Some(current);
+        if fits {
+            tetris.current_piece = Some(next);
+        }
+        fits
+    } else {
+        true
+    }
+}
+
+fn play_sound(sounds: Option<&SoundBank>, sfx: Sfx) {
+    if let Some(sounds) = sounds {
+        sounds.play(sfx);
+    }
+}
+
+fn play_lock_sound(sounds: Option<&SoundBank>, lines_cleared: u32) {
+    if lines_cleared >= 2 {
+        play_sound(sounds, Sfx::MultiLineClear);
+    } else if lines_cleared == 1 {
+        play_sound(sounds, Sfx::LineClear);
+    } else {
+        play_sound(sounds, Sfx::Lock);
+    }
+}
+
 fn update_vec(v: &mut Vec<u32>, value: u32) -> bool {
     if v.len() < NB_HIGHSOCRES {
         v.push(value);
@@ -153,41 +301,23 @@ fn update_vec(v: &mut Vec<u32>, value: u32) -> bool {
     false
 }
 
-fn print_game_information(tetris: &Tetris) {
-    let mut new_highest_score = true;
-    let mut new_highest_lines_sent = true;
+/// Folds each finished player's score/lines into the persisted tables and
+/// returns the (possibly updated) tables for the game-over screen to render.
+fn record_results(results: &[&Tetris]) -> (Vec<u32>, Vec<u32>) {
+    let (mut highscores, mut lines_sent) =
+        score::load_highscores_and_lines().unwrap_or_else(|| (Vec::new(), Vec::new()));
 
-    if let Some((mut highscores, mut lines_sent)) = score::load_highscores_and_lines() {
-        new_highest_score = update_vec(&mut highscores, tetris.score);
-        new_highest_lines_sent = update_vec(&mut lines_sent, tetris.nb_lines);
+    let mut changed = false;
+    for tetris in results {
+        changed |= update_vec(&mut highscores, tetris.score);
+        changed |= update_vec(&mut lines_sent, tetris.nb_lines);
+    }
 
-        if new_highest_score || new_highest_lines_sent {
-            score::save_highscores_and_lines(&highscores, &lines_sent);
-        }
-    } else {
-        score::save_highscores_and_lines(&[tetris.score], &[tetris.nb_lines]);
+    if changed {
+        score::save_highscores_and_lines(&highscores, &lines_sent);
     }
 
-    println!("Game over...");
-    println!(
-        "Score: {}{}",
-        tetris.score,
-        if new_highest_score {
-            " [NEW HIGHSCORE] "
-        } else {
-            ""
-        }
-    );
-    println!(
-        "Number of lines: {}{}",
-        tetris.nb_lines,
-        if new_highest_lines_sent {
-            " [NEW HIGHSCORE] "
-        } else {
-            ""
-        }
-    );
-    println!("Current level: {}", tetris.current_level);
+    (highscores, lines_sent)
 }
 
 fn display_game_information<'a>(
@@ -196,6 +326,9 @@ fn display_game_information<'a>(
     texture_creator: &'a TextureCreator<WindowContext>,
     font: &Font,
     start_x_pos: i32,
+    textures: &[Texture<'a>; 7],
+    next_pieces: &VecDeque<Tetrimino>,
+    held: Option<&Tetrimino>,
 ) {
     let score_text = format!("Score: {}", tetris.score);
     let lines_set_text = format!("Lines sent: {}", tetris.nb_lines);
@@ -229,9 +362,271 @@ fn display_game_information<'a>(
             get_rect_from_text(&level_text, start_x_pos, 160),
         )
         .expect("Couldn't copy text");
+
+    let mut y = 200;
+    let next_text = "Next:";
+    let next_label = create_texture_from_text(&texture_creator, &font, next_text, 255, 255, 255)
+        .expect("Cannot render text");
+    canvas
+        .copy(&next_label, None, get_rect_from_text(next_text, start_x_pos, y))
+        .expect("Couldn't copy text");
+    y += 35;
+
+    for piece in next_pieces.iter() {
+        draw_piece_preview(canvas, textures, piece, start_x_pos, y);
+        y += PREVIEW_CELL as i32 * 4 + 10;
+    }
+
+    y += 10;
+    let hold_text = "Hold:";
+    let hold_label = create_texture_from_text(&texture_creator, &font, hold_text, 255, 255, 255)
+        .expect("Cannot render text");
+    canvas
+        .copy(&hold_label, None, get_rect_from_text(hold_text, start_x_pos, y))
+        .expect("Couldn't copy text");
+    y += 35;
+
+    if let Some(piece) = held {
+        draw_piece_preview(canvas, textures, piece, start_x_pos, y);
+    }
+}
+
+fn draw_piece_preview<'a>(
+    canvas: &mut Canvas<Window>,
+    textures: &[Texture<'a>; 7],
+    piece: &Tetrimino,
+    x: i32,
+    y: i32,
+) {
+    for (line_nb, line) in piece.states[0].iter().enumerate() {
+        for (case_nb, case) in line.iter().enumerate() {
+            if *case == 0 {
+                continue;
+            }
+
+            canvas
+                .copy(
+                    &textures[*case as usize - 1],
+                    None,
+                    Rect::new(
+                        x + case_nb as i32 * PREVIEW_CELL as i32,
+                        y + line_nb as i32 * PREVIEW_CELL as i32,
+                        PREVIEW_CELL,
+                        PREVIEW_CELL,
+                    ),
+                )
+                .expect("Couldn't copy texture into window");
+        }
+    }
+}
+
+fn draw_player_board<'a>(
+    tetris: &Tetris,
+    canvas: &mut Canvas<Window>,
+    texture_creator: &'a TextureCreator<WindowContext>,
+    font: &Font,
+    textures: &[Texture<'a>; 7],
+    grid: &Texture,
+    border: &Texture,
+    grid_x: i32,
+    grid_y: i32,
+    info_x: i32,
+    next_pieces: &VecDeque<Tetrimino>,
+    held: Option<&Tetrimino>,
+) {
+    display_game_information(
+        tetris,
+        canvas,
+        texture_creator,
+        font,
+        info_x,
+        textures,
+        next_pieces,
+        held,
+    );
+
+    canvas
+        .copy(
+            border,
+            None,
+            Rect::new(
+                grid_x - 10,
+                grid_y - 10,
+                TETRIS_HEIGHT as u32 * 10 + 20,
+                TETRIS_HEIGHT as u32 * 16 + 20,
+            ),
+        )
+        .expect("Render failed");
+
+    canvas
+        .copy(
+            grid,
+            None,
+            Rect::new(
+                grid_x,
+                grid_y,
+                TETRIS_HEIGHT as u32 * 10,
+                TETRIS_HEIGHT as u32 * 16,
+            ),
+        )
+        .expect("Render failed");
+
+    if let Some(ref piece) = tetris.current_piece {
+        for (line_nb, line) in piece.states[piece.current_state as usize]
+            .iter()
+            .enumerate()
+        {
+            for (case_nb, case) in line.iter().enumerate() {
+                if *case == 0 {
+                    continue;
+                }
+
+                canvas
+                    .copy(
+                        &textures[*case as usize - 1],
+                        None,
+                        Rect::new(
+                            grid_x + (piece.x + case_nb as isize) as i32 * TETRIS_HEIGHT as i32,
+                            grid_y + (piece.y + line_nb) as i32 * TETRIS_HEIGHT as i32,
+                            TETRIS_HEIGHT as u32,
+                            TETRIS_HEIGHT as u32,
+                        ),
+                    )
+                    .expect("Couldn't copy texture into window");
+            }
+        }
+    }
+
+    for (line_nb, line) in tetris.game_map.iter().enumerate() {
+        for (case_nb, case) in line.iter().enumerate() {
+            if *case == 0 {
+                continue;
+            }
+
+            canvas
+                .copy(
+                    &textures[*case as usize - 1],
+                    None,
+                    Rect::new(
+                        grid_x + case_nb as i32 * TETRIS_HEIGHT as i32,
+                        grid_y + line_nb as i32 * TETRIS_HEIGHT as i32,
+                        TETRIS_HEIGHT as u32,
+                        TETRIS_HEIGHT as u32,
+                    ),
+                )
+                .expect("Couldn't copy texture into window");
+        }
+    }
+}
+
+fn draw_centered_text(
+    canvas: &mut Canvas<Window>,
+    texture_creator: &TextureCreator<WindowContext>,
+    font: &Font,
+    text: &str,
+    window_width: i32,
+    y: i32,
+) {
+    let texture =
+        create_texture_from_text(texture_creator, font, text, 255, 255, 255).expect("Cannot render text");
+    let rect = get_rect_from_text(text, 0, y).expect("Couldn't build text rect");
+    let x = (window_width - rect.width() as i32) / 2;
+    canvas
+        .copy(&texture, None, Rect::new(x, y, rect.width(), rect.height()))
+        .expect("Couldn't copy text");
+}
+
+fn draw_title_screen(
+    canvas: &mut Canvas<Window>,
+    texture_creator: &TextureCreator<WindowContext>,
+    font: &Font,
+    window_width: i32,
+) {
+    draw_centered_text(canvas, texture_creator, font, "TETRIS", window_width, 200);
+    draw_centered_text(
+        canvas,
+        texture_creator,
+        font,
+        "Press Enter for 1 player",
+        window_width,
+        260,
+    );
+    draw_centered_text(
+        canvas,
+        texture_creator,
+        font,
+        "Press V for 2 player versus",
+        window_width,
+        295,
+    );
+    draw_centered_text(
+        canvas,
+        texture_creator,
+        font,
+        "Press Escape to quit",
+        window_width,
+        330,
+    );
+}
+
+fn draw_pause_screen(
+    canvas: &mut Canvas<Window>,
+    texture_creator: &TextureCreator<WindowContext>,
+    font: &Font,
+    window_width: i32,
+    pause_key: Keycode,
+) {
+    draw_centered_text(canvas, texture_creator, font, "PAUSED", window_width, 200);
+    draw_centered_text(
+        canvas,
+        texture_creator,
+        font,
+        &format!("Press {} to resume", pause_key),
+        window_width,
+        260,
+    );
+}
+
+fn draw_game_over_screen(
+    canvas: &mut Canvas<Window>,
+    texture_creator: &TextureCreator<WindowContext>,
+    font: &Font,
+    window_width: i32,
+    highscores: &[u32],
+    lines_sent: &[u32],
+) {
+    draw_centered_text(canvas, texture_creator, font, "GAME OVER", window_width, 120);
+
+    let mut y = 180;
+    draw_centered_text(canvas, texture_creator, font, "High scores", window_width, y);
+    y += 35;
+    for score in highscores.iter().rev() {
+        draw_centered_text(canvas, texture_creator, font, &score.to_string(), window_width, y);
+        y += 30;
+    }
+
+    y += 20;
+    draw_centered_text(canvas, texture_creator, font, "Lines sent", window_width, y);
+    y += 35;
+    for lines in lines_sent.iter().rev() {
+        draw_centered_text(canvas, texture_creator, font, &lines.to_string(), window_width, y);
+        y += 30;
+    }
+
+    y += 20;
+    draw_centered_text(
+        canvas,
+        texture_creator,
+        font,
+        "Press Enter for the title screen",
+        window_width,
+        y,
+    );
 }
 
 pub fn main() {
+    let controls = config::load_or_create();
+
     let sdl_context = sdl2::init().expect("SDL initialization failed");
     let video_subsystem = sdl_context
         .video()
@@ -242,16 +637,58 @@ pub fn main() {
         .load_font("assets/catamaran_regular.ttf", 128)
         .expect("Couldn't load the font");
 
-    let width = 600;
+    let _audio_subsystem = sdl_context
+        .audio()
+        .expect("Couldn't get SDL audio subsystem");
+    let sounds = if controls.audio_enabled {
+        SoundBank::new().ok()
+    } else {
+        None
+    };
+
+    let board_width = 600;
+    let mut width = board_width;
     let height = 800;
-    let mut tetris = Tetris::new();
-    let mut timer = SystemTime::now();
+    let mut player_one = Tetris::new();
+    let mut player_two = Tetris::new();
+    player_one.current_level = controls.starting_level;
+    player_two.current_level = controls.starting_level;
+    let mut player_one_timer = SystemTime::now();
+    let mut player_two_timer = SystemTime::now();
+
+    let mut player_one_queue: VecDeque<Tetrimino> = (0..NEXT_QUEUE_LEN)
+        .map(|_| player_one.create_new_tetrimino())
+        .collect();
+    let mut player_two_queue: VecDeque<Tetrimino> = (0..NEXT_QUEUE_LEN)
+        .map(|_| player_two.create_new_tetrimino())
+        .collect();
+    let mut player_one_held: Option<Tetrimino> = None;
+    let mut player_two_held: Option<Tetrimino> = None;
+    let mut player_one_hold_used = false;
+    let mut player_two_hold_used = false;
+
     let mut event_pump = sdl_context
         .event_pump()
         .expect("Failed to get SDL event pump");
 
-    let grid_x = 20;
+    let game_controller_subsystem = sdl_context
+        .game_controller()
+        .expect("Couldn't get SDL game controller subsystem");
+    let joystick_count = game_controller_subsystem
+        .num_joysticks()
+        .unwrap_or_default();
+    let connected_controllers: Vec<GameController> = (0..joystick_count)
+        .filter(|&id| game_controller_subsystem.is_game_controller(id))
+        .filter_map(|id| game_controller_subsystem.open(id).ok())
+        .collect();
+    let player_one_controller = connected_controllers.get(0);
+    let player_two_controller = connected_controllers.get(1);
+    let mut player_one_repeat = ControllerRepeat::new();
+    let mut player_two_repeat = ControllerRepeat::new();
+
     let grid_y = (height - TETRIS_HEIGHT as u32 * 16) as i32 / 2;
+    let player_one_grid_x = 20;
+    let player_two_grid_x = board_width as i32 + 20;
 
     let window = video_subsystem
         .window("Tetris", width, height)
@@ -313,128 +750,330 @@ pub fn main() {
         texture!(45, 216, 47),
     ];
 
+    let mut game_state = GameState::Title;
+    let mut game_mode = GameMode::Solo;
+    let mut game_over_scores: Option<(Vec<u32>, Vec<u32>)> = None;
+
     loop {
-        if tetrimino::is_time_over(&tetris, &timer) {
-            let mut make_permanent = false;
-            if let Some(ref mut piece) = tetris.current_piece {
-                let x = piece.x;
-                let y = piece.y + 1;
-                make_permanent = !piece.change_position(&tetris.game_map, x, y);
-            }
+        canvas.set_draw_color(Color::RGB(255, 0, 0));
+        canvas.clear();
+
+        let events: Vec<Event> = event_pump.poll_iter().collect();
+        let mut quit = false;
+
+        match game_state {
+            GameState::Title => {
+                for event in &events {
+                    match event {
+                        Event::Quit { .. }
+                        | Event::KeyDown {
+                            keycode: Some(Keycode::Escape),
+                            ..
+                        } => quit = true,
+                        Event::KeyDown {
+                            keycode: Some(Keycode::Return),
+                            ..
+                        } => {
+                            game_mode = GameMode::Solo;
+                            width = board_width;
+                            canvas
+                                .window_mut()
+                                .set_size(width, height)
+                                .expect("Failed to resize window");
+                            player_one = Tetris::new();
+                            player_two = Tetris::new();
+                            player_one.current_level = controls.starting_level;
+                            player_two.current_level = controls.starting_level;
+                            player_one_timer = SystemTime::now();
+                            player_two_timer = SystemTime::now();
+                            player_one_queue = (0..NEXT_QUEUE_LEN)
+                                .map(|_| player_one.create_new_tetrimino())
+                                .collect();
+                            player_two_queue = (0..NEXT_QUEUE_LEN)
+                                .map(|_| player_two.create_new_tetrimino())
+                                .collect();
+                            player_one_held = None;
+                            player_two_held = None;
+                            player_one_hold_used = false;
+                            player_two_hold_used = false;
+                            game_state = GameState::Playing;
+                        }
+                        Event::KeyDown {
+                            keycode: Some(Keycode::V),
+                            ..
+                        } => {
+                            game_mode = GameMode::Versus;
+                            width = board_width * 2;
+                            canvas
+                                .window_mut()
+                                .set_size(width, height)
+                                .expect("Failed to resize window");
+                            player_one = Tetris::new();
+                            player_two = Tetris::new();
+                            player_one.current_level = controls.starting_level;
+                            player_two.current_level = controls.starting_level;
+                            player_one_timer = SystemTime::now();
+                            player_two_timer = SystemTime::now();
+                            player_one_queue = (0..NEXT_QUEUE_LEN)
+                                .map(|_| player_one.create_new_tetrimino())
+                                .collect();
+                            player_two_queue = (0..NEXT_QUEUE_LEN)
+                                .map(|_| player_two.create_new_tetrimino())
+                                .collect();
+                            player_one_held = None;
+                            player_two_held = None;
+                            player_one_hold_used = false;
+                            player_two_hold_used = false;
+                            game_state = GameState::Playing;
+                        }
+                        _ => {}
+                    }
+                }
 
-            if make_permanent {
-                tetris.make_permanent();
+                draw_title_screen(&mut canvas, &texture_creator, &font, width as i32);
             }
+            GameState::Paused => {
+                for event in &events {
+                    match event {
+                        Event::Quit { .. }
+                        | Event::KeyDown {
+                            keycode: Some(Keycode::Escape),
+                            ..
+                        } => quit = true,
+                        Event::KeyDown {
+                            keycode: Some(keycode),
+                            ..
+                        } if *keycode == controls.pause => {
+                            player_one_timer = SystemTime::now();
+                            player_two_timer = SystemTime::now();
+                            game_state = GameState::Playing;
+                        }
+                        _ => {}
+                    }
+                }
 
-            timer = SystemTime::now();
-        }
+                draw_pause_screen(
+                    &mut canvas,
+                    &texture_creator,
+                    &font,
+                    width as i32,
+                    controls.pause,
+                );
+            }
+            GameState::GameOver => {
+                for event in &events {
+                    match event {
+                        Event::Quit { .. }
+                        | Event::KeyDown {
+                            keycode: Some(Keycode::Escape),
+                            ..
+                        } => quit = true,
+                        Event::KeyDown {
+                            keycode: Some(Keycode::Return),
+                            ..
+                        } => game_state = GameState::Title,
+                        _ => {}
+                    }
+                }
 
-        // We need to draw the tetrimino grid in here
-        canvas.set_draw_color(Color::RGB(255, 0, 0));
-        canvas.clear();
+                let (highscores, lines_sent) = game_over_scores.get_or_insert_with(|| {
+                    if game_mode == GameMode::Versus {
+                        record_results(&[&player_one, &player_two])
+                    } else {
+                        record_results(&[&player_one])
+                    }
+                });
+                draw_game_over_screen(
+                    &mut canvas,
+                    &texture_creator,
+                    &font,
+                    width as i32,
+                    highscores,
+                    lines_sent,
+                );
+            }
+            GameState::Playing => {
+                for event in &events {
+                    if let Event::KeyDown {
+                        keycode: Some(keycode),
+                        ..
+                    } = event
+                    {
+                        if *keycode == controls.pause {
+                            game_state = GameState::Paused;
+                        }
+                    }
+                }
 
-        display_game_information(
-            &tetris,
-            &mut canvas,
-            &texture_creator,
-            &font,
-            width as i32 - grid_x - 130,
-        );
+                let mut round_over = false;
 
-        canvas
-            .copy(
-                &border,
-                None,
-                Rect::new(
-                    10,
-                    (height - TETRIS_HEIGHT as u32 * 16) as i32 / 2 - 10,
-                    TETRIS_HEIGHT as u32 * 10 + 20,
-                    TETRIS_HEIGHT as u32 * 16 + 20,
-                ),
-            )
-            .expect("Render failed");
+                if tetrimino::is_time_over(&player_one, &player_one_timer) {
+                    let mut make_permanent = false;
+                    if let Some(ref mut piece) = player_one.current_piece {
+                        let x = piece.x;
+                        let y = piece.y + 1;
+                        make_permanent = !piece.change_position(&player_one.game_map, x, y);
+                    }
 
-        canvas
-            .copy(
-                &grid,
-                None,
-                Rect::new(
-                    20,
-                    (height - TETRIS_HEIGHT as u32 * 16) as i32 / 2,
-                    TETRIS_HEIGHT as u32 * 10,
-                    TETRIS_HEIGHT as u32 * 16,
-                ),
-            )
-            .expect("Render failed");
+                    if make_permanent {
+                        let lines_before = player_one.nb_lines;
+                        player_one.make_permanent();
+                        let cleared = player_one.nb_lines - lines_before;
+                        play_lock_sound(sounds.as_ref(), cleared);
+                        if cleared >= 2 && !send_garbage(&mut player_two, cleared - 1) {
+                            play_sound(sounds.as_ref(), Sfx::GameOver);
+                            round_over = true;
+                        }
+                        player_one_hold_used = false;
+                    }
 
-        if tetris.current_piece.is_none() {
-            let current_piece = tetris.create_new_tetrimino();
-            if !current_piece.test_current_position(&tetris.game_map) {
-                print_game_information(&tetris);
-                break;
-            }
-            tetris.current_piece = Some(current_piece);
-        }
+                    player_one_timer = SystemTime::now();
+                }
 
-        let mut quit = false;
-        if !handle_events(&mut tetris, &mut quit, &mut timer, &mut event_pump) {
-            if let Some(ref mut piece) = tetris.current_piece {
-                // We need to draw our current tetrimono in here
-                for (line_nb, line) in piece.states[piece.current_state as usize]
-                    .iter()
-                    .enumerate()
+                if game_mode == GameMode::Versus
+                    && !round_over
+                    && tetrimino::is_time_over(&player_two, &player_two_timer)
                 {
-                    for (case_nb, case) in line.iter().enumerate() {
-                        if *case == 0 {
-                            continue;
+                    let mut make_permanent = false;
+                    if let Some(ref mut piece) = player_two.current_piece {
+                        let x = piece.x;
+                        let y = piece.y + 1;
+                        make_permanent = !piece.change_position(&player_two.game_map, x, y);
+                    }
+
+                    if make_permanent {
+                        let lines_before = player_two.nb_lines;
+                        player_two.make_permanent();
+                        let cleared = player_two.nb_lines - lines_before;
+                        play_lock_sound(sounds.as_ref(), cleared);
+                        if cleared >= 2 && !send_garbage(&mut player_one, cleared - 1) {
+                            play_sound(sounds.as_ref(), Sfx::GameOver);
+                            round_over = true;
                         }
+                        player_two_hold_used = false;
+                    }
+
+                    player_two_timer = SystemTime::now();
+                }
 
-                        canvas
-                            .copy(
-                                &textures[*case as usize - 1],
-                                None,
-                                Rect::new(
-                                    grid_x
-                                        + (piece.x + case_nb as isize) as i32
-                                            * TETRIS_HEIGHT as i32,
-                                    grid_y + (piece.y + line_nb) as i32 * TETRIS_HEIGHT as i32,
-                                    TETRIS_HEIGHT as u32,
-                                    TETRIS_HEIGHT as u32,
-                                ),
-                            )
-                            .expect("Couldn't copy texture into window");
+                if !round_over && player_one.current_piece.is_none() {
+                    let current_piece = player_one_queue
+                        .pop_front()
+                        .unwrap_or_else(|| player_one.create_new_tetrimino());
+                    player_one_queue.push_back(player_one.create_new_tetrimino());
+                    if !current_piece.test_current_position(&player_one.game_map) {
+                        play_sound(sounds.as_ref(), Sfx::GameOver);
+                        round_over = true;
+                    } else {
+                        player_one.current_piece = Some(current_piece);
                     }
                 }
-            }
-        }
 
-        if quit {
-            print_game_information(&tetris);
-            break;
-        }
+                if game_mode == GameMode::Versus && !round_over && player_two.current_piece.is_none() {
+                    let current_piece = player_two_queue
+                        .pop_front()
+                        .unwrap_or_else(|| player_two.create_new_tetrimino());
+                    player_two_queue.push_back(player_two.create_new_tetrimino());
+                    if !current_piece.test_current_position(&player_two.game_map) {
+                        play_sound(sounds.as_ref(), Sfx::GameOver);
+                        round_over = true;
+                    } else {
+                        player_two.current_piece = Some(current_piece);
+                    }
+                }
 
-        // We need to draw the game map in here
-        for (line_nb, line) in tetris.game_map.iter().enumerate() {
-            for (case_nb, case) in line.iter().enumerate() {
-                if *case == 0 {
-                    continue;
+                if !round_over {
+                    let lines_before = player_one.nb_lines;
+                    handle_events(
+                        &mut player_one,
+                        &mut quit,
+                        &mut player_one_timer,
+                        &events,
+                        &controls.player_one,
+                        player_one_controller,
+                        &mut player_one_repeat,
+                        sounds.as_ref(),
+                        &mut player_one_queue,
+                        &mut player_one_held,
+                        &mut player_one_hold_used,
+                        &mut round_over,
+                    );
+                    let cleared = player_one.nb_lines - lines_before;
+                    if game_mode == GameMode::Versus
+                        && cleared >= 2
+                        && !send_garbage(&mut player_two, cleared - 1)
+                    {
+                        play_sound(sounds.as_ref(), Sfx::GameOver);
+                        round_over = true;
+                    }
+
+                    if game_mode == GameMode::Versus {
+                        let lines_before = player_two.nb_lines;
+                        handle_events(
+                            &mut player_two,
+                            &mut quit,
+                            &mut player_two_timer,
+                            &events,
+                            &controls.player_two,
+                            player_two_controller,
+                            &mut player_two_repeat,
+                            sounds.as_ref(),
+                            &mut player_two_queue,
+                            &mut player_two_held,
+                            &mut player_two_hold_used,
+                            &mut round_over,
+                        );
+                        let cleared = player_two.nb_lines - lines_before;
+                        if cleared >= 2 && !send_garbage(&mut player_one, cleared - 1) {
+                            play_sound(sounds.as_ref(), Sfx::GameOver);
+                            round_over = true;
+                        }
+                    }
                 }
 
-                canvas
-                    .copy(
-                        &textures[*case as usize - 1],
-                        None,
-                        Rect::new(
-                            grid_x + case_nb as i32 * TETRIS_HEIGHT as i32,
-                            grid_y + line_nb as i32 * TETRIS_HEIGHT as i32,
-                            TETRIS_HEIGHT as u32,
-                            TETRIS_HEIGHT as u32,
-                        ),
-                    )
-                    .expect("Couldn't copy texture into window");
+                draw_player_board(
+                    &player_one,
+                    &mut canvas,
+                    &texture_creator,
+                    &font,
+                    &textures,
+                    &grid,
+                    &border,
+                    player_one_grid_x,
+                    grid_y,
+                    board_width as i32 - player_one_grid_x - 130,
+                    &player_one_queue,
+                    player_one_held.as_ref(),
+                );
+
+                if game_mode == GameMode::Versus {
+                    draw_player_board(
+                        &player_two,
+                        &mut canvas,
+                        &texture_creator,
+                        &font,
+                        &textures,
+                        &grid,
+                        &border,
+                        player_two_grid_x,
+                        grid_y,
+                        width as i32 - player_one_grid_x - 130,
+                        &player_two_queue,
+                        player_two_held.as_ref(),
+                    );
+                }
+
+                if round_over {
+                    game_over_scores = None;
+                    game_state = GameState::GameOver;
+                }
             }
         }
 
+        if quit {
+            break;
+        }
+
         canvas.present();
         thread::sleep(Duration::new(0, 1_000_000_000u32 / 60));
     }
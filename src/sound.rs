@@ -0,0 +1,71 @@
+use std::path::Path;
+
+use sdl2::mixer::{self, Channel, Chunk, Music, DEFAULT_CHANNELS};
+
+const AUDIO_FREQUENCY: i32 = 44_100;
+const AUDIO_CHUNK_SIZE: i32 = 1_024;
+const MIXER_CHANNELS: i32 = 8;
+
+/// Sound effects triggered by gameplay events.
+#[derive(Clone, Copy)]
+pub enum Sfx {
+    Lock,
+    LineClear,
+    MultiLineClear,
+    Rotate,
+    HardDrop,
+    GameOver,
+}
+
+/// Owns every `Chunk`/`Music` for the lifetime of the game so SDL never frees
+/// audio data out from under a still-playing channel.
+pub struct SoundBank {
+    theme: Music<'static>,
+    lock: Chunk,
+    line_clear: Chunk,
+    multi_line_clear: Chunk,
+    rotate: Chunk,
+    hard_drop: Chunk,
+    game_over: Chunk,
+}
+
+impl SoundBank {
+    pub fn new() -> Result<SoundBank, String> {
+        mixer::open_audio(AUDIO_FREQUENCY, mixer::DEFAULT_FORMAT, DEFAULT_CHANNELS, AUDIO_CHUNK_SIZE)?;
+        mixer::allocate_channels(MIXER_CHANNELS);
+
+        let theme = Music::from_file(Path::new("assets/sounds/theme.ogg"))?;
+        theme.play(-1)?;
+
+        Ok(SoundBank {
+            theme,
+            lock: Chunk::from_file(Path::new("assets/sounds/lock.wav"))?,
+            line_clear: Chunk::from_file(Path::new("assets/sounds/line_clear.wav"))?,
+            multi_line_clear: Chunk::from_file(Path::new("assets/sounds/line_clear_multi.wav"))?,
+            rotate: Chunk::from_file(Path::new("assets/sounds/rotate.wav"))?,
+            hard_drop: Chunk::from_file(Path::new("assets/sounds/hard_drop.wav"))?,
+            game_over: Chunk::from_file(Path::new("assets/sounds/game_over.wav"))?,
+        })
+    }
+
+    pub fn play(&self, sfx: Sfx) {
+        let chunk = match sfx {
+            Sfx::Lock => &self.lock,
+            Sfx::LineClear => &self.line_clear,
+            Sfx::MultiLineClear => &self.multi_line_clear,
+            Sfx::Rotate => &self.rotate,
+            Sfx::HardDrop => &self.hard_drop,
+            Sfx::GameOver => &self.game_over,
+        };
+
+        if let Err(err) = Channel::all().play(chunk, 0) {
+            eprintln!("Couldn't play sound effect: {}", err);
+        }
+    }
+}
+
+impl Drop for SoundBank {
+    fn drop(&mut self) {
+        self.theme.halt();
+    }
+}
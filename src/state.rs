@@ -0,0 +1,17 @@
+/// Top-level states driving the main loop: the title menu, live gameplay,
+/// the pause overlay, and the post-round high-score screen.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum GameState {
+    Title,
+    Playing,
+    Paused,
+    GameOver,
+}
+
+/// Chosen from the title screen: a single board, or both boards racing
+/// against each other with garbage-line exchange.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum GameMode {
+    Solo,
+    Versus,
+}
@@ -0,0 +1,47 @@
+use rand::Rng;
+use sdl2::keyboard::Keycode;
+
+use crate::tetrimino::Tetris;
+
+/// Per-player key mapping so both halves of the split-screen can be polled
+/// from the same frame's events without colliding on keycodes.
+pub struct KeyBindings {
+    pub left: Keycode,
+    pub right: Keycode,
+    pub down: Keycode,
+    pub rotate: Keycode,
+    pub drop: Keycode,
+    pub hold: Keycode,
+}
+
+const GARBAGE_CELL: u8 = 7;
+
+/// Pushes `rows` solid garbage rows (one random gap column each) onto the
+/// bottom of `tetris`'s map, shifting the existing rows up and off the top.
+/// The falling piece, if any, is shifted up by the same amount (stopping at
+/// the top row) and
+/// re-validated against the new stack so it doesn't end up buried in or
+/// floating above the rows that were just inserted underneath it. Returns
+/// `false` if the shifted piece no longer fits, i.e. the garbage topped the
+/// receiving player out.
+pub fn send_garbage(tetris: &mut Tetris, rows: u32) -> bool {
+    let width = tetris.game_map.get(0).map(Vec::len).unwrap_or(10);
+    let mut rng = rand::thread_rng();
+
+    for _ in 0..rows {
+        let gap = rng.gen_range(0..width);
+        let mut garbage_row = vec![GARBAGE_CELL; width];
+        garbage_row[gap] = 0;
+
+        tetris.game_map.remove(0);
+        tetris.game_map.push(garbage_row);
+    }
+
+    if let Some(ref mut piece) = tetris.current_piece {
+        let x = piece.x;
+        let y = piece.y.saturating_sub(rows as usize);
+        piece.change_position(&tetris.game_map, x, y)
+    } else {
+        true
+    }
+}